@@ -3,10 +3,23 @@
 //! This is an example library demonstrating various attributes from the
 //! stability crate.
 
+stability::features! {
+    risky_function => "#101",
+    risky_struct => "#102",
+    risky_private_function => "#104",
+    experimental => "#105",
+    risky_method => "#106",
+    risky_trait_method => "#107",
+}
+
 /// This function does something really risky!
 ///
 /// Don't use it yet!
-#[stability::unstable(feature = "risky-function", issue = "#101")]
+#[stability::unstable(
+    feature = "risky-function",
+    issue = 101,
+    reason = "the argument list is still being bikeshedded"
+)]
 pub fn risky_function() {
     unimplemented!()
 }
@@ -19,6 +32,34 @@ pub struct RiskyStruct {
     pub x: u8,
 }
 
+impl RiskyStruct {
+    /// This method is stable.
+    pub fn x(&self) -> u8 {
+        self.x
+    }
+
+    /// This method is still being designed, and is gated behind its own
+    /// feature, separate from the one that gates `RiskyStruct` itself.
+    #[stability::unstable(feature = "risky-method", issue = "#106")]
+    pub fn risky_method(&self) -> u8 {
+        unimplemented!()
+    }
+}
+
+/// A trait with a method that is still being designed.
+pub trait RiskyTrait {
+    /// This method is stable.
+    fn stable_method(&self);
+
+    /// This method is still being designed. Implementors don't need to
+    /// implement it: it has a default body whether or not the
+    /// `risky-trait-method` feature is enabled.
+    #[stability::unstable(feature = "risky-trait-method", issue = "#107")]
+    fn risky_trait_method(&self) {
+        unimplemented!()
+    }
+}
+
 mod private {
     /// This function does something really risky!
     ///
@@ -32,3 +73,42 @@ mod private {
 #[allow(unused_imports)]
 #[stability::unstable(feature = "risky-private-function")]
 pub use private::risky_private_function;
+
+/// This function used to be risky, but isn't anymore.
+#[stability::stable(feature = "safe-function", since = "1.0.0", issue = "#103")]
+pub fn safe_function() {
+    unimplemented!()
+}
+
+/// This function was never finished, and has been abandoned in favor of
+/// [`safe_function`].
+#[stability::removed(
+    feature = "risky-abandoned-function",
+    since = "1.0.0",
+    note = "use `safe_function` instead",
+    issue = "#108"
+)]
+pub fn risky_abandoned_function() {
+    unimplemented!()
+}
+
+/// An entire subsystem that is still being designed.
+///
+/// Every public item in this module is gated behind the `experimental`
+/// feature without having to repeat the attribute on each one.
+#[stability::unstable(feature = "experimental", issue = "#105", recursive)]
+pub mod experimental {
+    pub fn do_something() {
+        unimplemented!()
+    }
+
+    pub struct Handle {
+        pub id: u64,
+    }
+
+    pub mod inner {
+        pub fn nested() {
+            unimplemented!()
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream, Result};
+use syn::punctuated::Punctuated;
+use syn::{Ident, LitStr, Token};
+
+/// A single `name => "issue"` entry in a [`features!`](crate::features) block.
+struct FeatureEntry {
+    name: Ident,
+    issue: LitStr,
+}
+
+impl Parse for FeatureEntry {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let name = input.parse()?;
+        input.parse::<Token![=>]>()?;
+        let issue = input.parse()?;
+
+        Ok(FeatureEntry { name, issue })
+    }
+}
+
+struct FeatureList {
+    entries: Punctuated<FeatureEntry, Token![,]>,
+}
+
+impl Parse for FeatureList {
+    fn parse(input: ParseStream) -> Result<Self> {
+        Ok(FeatureList {
+            entries: Punctuated::parse_terminated(input)?,
+        })
+    }
+}
+
+pub(crate) fn expand(input: TokenStream) -> TokenStream {
+    let FeatureList { entries } = syn::parse_macro_input!(input as FeatureList);
+
+    // Each declared feature gets a zero-sized marker const. `#[unstable]`
+    // expands a reference to the matching const, so a typo'd or undeclared
+    // feature name fails to compile instead of silently doing nothing.
+    let markers = entries.iter().map(|entry| {
+        let name = &entry.name;
+
+        quote! {
+            #[allow(non_upper_case_globals)]
+            pub(crate) const #name: () = ();
+        }
+    });
+
+    let table_rows: Vec<String> = entries
+        .iter()
+        .map(|entry| format!("| `{}` | {} |", entry.name, entry.issue.value()))
+        .collect();
+
+    let table_doc = format!(
+        "# Unstable features\n\
+        \n\
+        This is the list of every unstable feature declared by this crate, \
+        along with its tracking issue.\n\
+        \n\
+        | Feature | Tracking issue |\n\
+        | --- | --- |\n\
+        {}",
+        table_rows.join("\n")
+    );
+
+    TokenStream::from(quote! {
+        #[doc = #table_doc]
+        pub(crate) mod __unstable_features {
+            #(#markers)*
+        }
+    })
+}
@@ -0,0 +1,55 @@
+use std::env;
+
+use syn::meta::ParseNestedMeta;
+use syn::parse::Result;
+
+/// A tracking issue referenced by `#[unstable]` or `#[stable]`.
+///
+/// A bare numeric issue (e.g. `issue = 101`) is rendered as a clickable
+/// Markdown link, using the crate-wide `STABILITY_ISSUE_URL` environment
+/// variable as a template (read at macro-expansion time, so it is typically
+/// set by a build script via `cargo:rustc-env=STABILITY_ISSUE_URL=...`).
+/// Anything else is treated as a free-form reference, for trackers other
+/// than GitHub, and rendered verbatim.
+#[derive(Debug, Clone)]
+pub(crate) enum Issue {
+    Numeric(u64),
+    Named(String),
+}
+
+impl Issue {
+    pub(crate) fn parse(meta: &ParseNestedMeta) -> Result<Self> {
+        let lit: syn::Lit = meta.value()?.parse()?;
+
+        match lit {
+            syn::Lit::Str(s) => Ok(Issue::Named(s.value())),
+            syn::Lit::Int(i) => {
+                let n: u64 = i.base10_parse()?;
+
+                if n == 0 {
+                    return Err(meta.error("issue number must be non-zero"));
+                }
+
+                Ok(Issue::Numeric(n))
+            }
+            other => Err(syn::Error::new_spanned(
+                other,
+                "expected `issue` to be a string or an integer literal",
+            )),
+        }
+    }
+
+    /// Render this issue as a Markdown fragment for inclusion in an
+    /// Availability doc section.
+    pub(crate) fn to_markdown(&self) -> String {
+        match self {
+            Issue::Named(name) => format!("`{}`", name),
+            Issue::Numeric(n) => match env::var("STABILITY_ISSUE_URL") {
+                Ok(template) if template.contains("{}") => {
+                    format!("[#{}]({})", n, template.replace("{}", &n.to_string()))
+                }
+                _ => format!("`#{}`", n),
+            },
+        }
+    }
+}
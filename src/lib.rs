@@ -15,13 +15,24 @@
 //! crate](https://github.com/sagebind/stability/tree/master/example) included
 //! in the stability repository.
 //!
-//! Currently, only the [`#[unstable]`][macro@unstable] attribute is available.
-//! Please see the documentation of that macro for an explanation on what it
-//! does and how to use it.
+//! Three attributes are provided: [`#[unstable]`][macro@unstable] for
+//! items that are not yet ready for general use,
+//! [`#[stable]`][macro@stable] for marking the point at which an item's API
+//! was finalized, and [`#[removed]`][macro@removed] for unstable features
+//! that have since been retired. Please see the documentation of each macro
+//! for an explanation on what it does and how to use it.
+//!
+//! The [`features!`][macro@features] macro declares every unstable feature
+//! a crate has up front, in one place, so that `#[unstable]` can validate
+//! feature names at compile time.
 
 use proc_macro::TokenStream;
-use syn::{parse_macro_input, Item};
+use syn::{parse_macro_input, ImplItem, Item, TraitItem};
 
+mod features;
+mod issue;
+mod removed;
+mod stable;
 mod unstable;
 
 /// Mark an API as unstable.
@@ -45,16 +56,21 @@ mod unstable;
 ///     as it might be desirable to be able to re-export them even if the module
 ///     visibility is restricted. You should apply the attribute to each item
 ///     within the module with the same feature name if you want to restrict the
-///     module's contents itself and not just the module namespace.
+///     module's contents itself and not just the module namespace, or pass the
+///     `recursive` argument to have this done for you automatically.
 /// - Appends an "Availability" section to the item's documentation that notes
 ///   that the item is unstable, and indicates the name of the crate feature to
 ///   enable it.
 ///
 /// Note that unlike the `#[unstable]` attribute used [in the standard
 /// library](https://rustc-dev-guide.rust-lang.org/stability.html), this
-/// attribute does not apply itself recursively to child items.
+/// attribute does not apply itself recursively to child items by default.
+/// Pass the `recursive` argument described below if you want that behavior.
 ///
-/// Applying this attribute to non-`pub` items is pointless and does nothing.
+/// Applying this attribute to a non-`pub` top-level item is pointless and
+/// does nothing. Applying it to a non-`pub` associated function, const, or
+/// type is a hard compile error instead; see the note on associated items
+/// below for why.
 ///
 /// # Arguments
 ///
@@ -64,15 +80,38 @@ mod unstable;
 /// - `feature`: Specify the name of the unstable feature that should control
 ///   this item's availability. The crate feature will have the string
 ///   `unstable-` prepended to it. If not specified, it will be guarded by a
-///   catch-all `unstable` feature.
+///   catch-all `unstable` feature. A named feature must have a matching
+///   entry in a [`features!`][macro@features] declaration somewhere in the
+///   crate, or the crate fails to compile.
 /// - `issue`: Provide a link or reference to a tracking issue for the unstable
-///   feature. This will be included in the item's documentation.
+///   feature. This will be included in the item's documentation. A bare
+///   integer (e.g. `issue = 101`) is rendered as a clickable link using the
+///   `STABILITY_ISSUE_URL` environment variable as a template, if it is set
+///   at macro-expansion time (for example via a build script's
+///   `cargo:rustc-env=STABILITY_ISSUE_URL=https://github.com/org/repo/issues/{}`).
+///   Without that variable set, or for non-numeric references to trackers
+///   other than GitHub, the issue is rendered verbatim instead.
+/// - `reason`: An explanation, in prose, of why the item is still gated.
+///   This is woven directly into the Availability section of the item's
+///   documentation (mirroring rustc's internal `unstable_reason`), so users
+///   have some context beyond "it's unstable" for why they can't use it yet.
+/// - `recursive`: Only meaningful on a module with inline content (`mod foo {
+///   .. }`). Instead of leaving child items alone, walk every public child
+///   item (descending into nested modules as well) and gate each one behind
+///   the same feature. Each gated child's Availability section repeats the
+///   `reason`, if any, but not the `issue`. Out-of-line child modules
+///   (`pub mod foo;`) are left as they are. This is useful for gating an entire experimental subsystem behind a
+///   single feature without annotating every item in it.
 ///
 /// # Examples
 ///
 /// We can apply the attribute to a public function like so:
 ///
 /// ```
+/// stability::features! {
+///     risky_function => "#101",
+/// }
+///
 /// /// This function does something really risky!
 /// ///
 /// /// Don't use it yet!
@@ -107,12 +146,248 @@ mod unstable;
 ///     unimplemented!()
 /// }
 /// ```
+///
+/// As well as top-level items, `#[unstable]` can also be applied to an
+/// associated function, associated const, or associated type inside an
+/// inherent `impl` block or a `trait` block, letting you gate a single
+/// member of an otherwise-stable type or trait. Since trait items have no
+/// visibility of their own to toggle, a gated trait method is instead given
+/// a default body (that panics) when its feature is disabled, so the trait
+/// remains implementable by existing consumers who haven't opted into it.
+///
+/// Items inside a *trait impl* block (`impl SomeTrait for Foo { .. }`) can
+/// never be written with a `pub` keyword of their own, which would make
+/// this attribute indistinguishable from being applied to a private
+/// inherent method and silently do nothing, always leaving the method
+/// fully exposed. Rather than risk that, a non-`pub` associated function,
+/// const, or type is a hard `compile_error!`, whether it's actually inside
+/// a trait impl or is just a pointlessly-gated private inherent item. Gate
+/// the method on the trait definition (or on the type, if it's an inherent
+/// method) instead.
+///
+/// Gating an associated item with a named feature looks just like gating a
+/// top-level item, `features!` declaration included:
+///
+/// ```
+/// stability::features! {
+///     risky_method => "#106",
+/// }
+///
+/// pub struct RiskyStruct {
+///     x: u8,
+/// }
+///
+/// impl RiskyStruct {
+///     /// This method is stable.
+///     pub fn x(&self) -> u8 {
+///         self.x
+///     }
+///
+///     /// This method is still being designed, even though `RiskyStruct`
+///     /// itself is stable.
+///     #[stability::unstable(feature = "risky-method")]
+///     pub fn risky_method(&self) -> u8 {
+///         unimplemented!()
+///     }
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn unstable(args: TokenStream, input: TokenStream) -> TokenStream {
     let mut attributes = unstable::UnstableAttribute::default();
     let attributes_parser = syn::meta::parser(|meta| attributes.parse(meta));
     parse_macro_input!(args with attributes_parser);
 
+    if let Ok(item) = syn::parse::<Item>(input.clone()) {
+        return match item {
+            Item::Type(item_type) => attributes.expand(item_type),
+            Item::Enum(item_enum) => attributes.expand(item_enum),
+            Item::Struct(item_struct) => attributes.expand(item_struct),
+            Item::Fn(item_fn) => attributes.expand(item_fn),
+            Item::Mod(item_mod) => attributes.expand_item_mod(item_mod),
+            Item::Trait(item_trait) => attributes.expand(item_trait),
+            Item::Const(item_const) => attributes.expand(item_const),
+            Item::Static(item_static) => attributes.expand(item_static),
+            Item::Use(item_use) => attributes.expand(item_use),
+            _ => panic!("unsupported item type"),
+        };
+    }
+
+    if let Ok(impl_item) = syn::parse::<ImplItem>(input.clone()) {
+        return match impl_item {
+            ImplItem::Fn(impl_item_fn) => attributes.expand(impl_item_fn),
+            ImplItem::Const(impl_item_const) => attributes.expand(impl_item_const),
+            ImplItem::Type(impl_item_type) => attributes.expand(impl_item_type),
+            _ => panic!("unsupported item type"),
+        };
+    }
+
+    match syn::parse::<TraitItem>(input) {
+        Ok(trait_item) => attributes.expand_trait_item(trait_item),
+        Err(err) => err.into_compile_error().into(),
+    }
+}
+
+/// Declare the set of unstable features a crate has, once, up front.
+///
+/// This is the single source of truth for every feature name that
+/// [`#[unstable(feature = "...")]`][macro@unstable] is allowed to reference.
+/// The generated markers are referenced by their absolute path, so invoke
+/// this macro exactly once, at the crate root:
+///
+/// ```
+/// stability::features! {
+///     risky_function => "#101",
+///     risky_struct => "#102",
+/// }
+/// ```
+///
+/// Each entry is a feature name (spelled as an identifier, using `_` where
+/// you would use `-` in the string passed to `#[unstable]`) followed by
+/// `=>` and its tracking issue. `#[unstable(feature = "risky-function")]`
+/// will then expand a reference to a marker generated for the
+/// `risky_function` entry; if the feature was never declared here, or its
+/// name was misspelled, the crate fails to compile instead of silently
+/// leaving the item ungated.
+///
+/// This macro also doubles as documentation: every feature and its
+/// tracking issue is rendered into a table on the generated (private)
+/// module, so there is always one place to look to see everything that is
+/// currently unstable.
+#[proc_macro]
+pub fn features(input: TokenStream) -> TokenStream {
+    features::expand(input)
+}
+
+/// Mark an API as stable.
+///
+/// This is the counterpart to [`#[unstable]`][macro@unstable]. Apply it to an
+/// item once its API has been finalized, to record the version it became
+/// stable in. Unlike `#[unstable]`, this attribute does not change the
+/// item's visibility; it is purely documentation, plus a best-effort
+/// compile-time check that the item isn't accidentally stabilized while
+/// something else in the crate still gates a feature of the same name
+/// behind `#[unstable]`.
+///
+/// **This check is not a guarantee.** Proc-macro invocations share no
+/// reliable state with each other, so it works by having `#[unstable]`
+/// record its feature name in a process-global set the first time it
+/// expands, and having `#[stable]` look for its own feature name in that
+/// set. This only catches the mistake if the `#[unstable]` item it's meant
+/// to catch has *already* expanded by the time `#[stable]`'s does, which in
+/// practice means the `#[unstable]` usage has to come earlier in the
+/// crate's source than the `#[stable]` one. Simply writing the `#[stable]`
+/// item first is enough to make the same violation compile cleanly with no
+/// diagnostic at all. Don't rely on this catching every case; treat it as a
+/// bonus sanity check, not a substitute for reviewing the diff yourself.
+///
+/// # Arguments
+///
+/// - `feature`: The name of the feature being stabilized, matching whatever
+///   was previously passed to `#[unstable(feature = "...")]`. This is
+///   required, so that the check described above has something concrete to
+///   look for instead of guessing a feature name from the item itself.
+/// - `since`: The crate version in which the item was stabilized. This is
+///   required.
+/// - `issue`: Provide a link or reference to the tracking issue that was
+///   used while the API was unstable. This will be included in the item's
+///   documentation.
+///
+/// # Examples
+///
+/// ```
+/// /// This function is safe and ready for general use.
+/// #[stability::stable(feature = "risky-function", since = "1.0.0")]
+/// pub fn risky_function() {
+///     unimplemented!()
+/// }
+/// ```
+///
+/// This will essentially be expanded to the following:
+///
+/// ```
+/// /// This function is safe and ready for general use.
+/// ///
+/// /// # Availability
+/// ///
+/// /// **Stabilized in version 1.0.0.**
+/// pub fn risky_function() {
+///     unimplemented!()
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn stable(args: TokenStream, input: TokenStream) -> TokenStream {
+    let mut attributes = stable::StableAttribute::default();
+    let attributes_parser = syn::meta::parser(|meta| attributes.parse(meta));
+    parse_macro_input!(args with attributes_parser);
+
+    match parse_macro_input!(input as Item) {
+        Item::Type(item_type) => attributes.expand(item_type),
+        Item::Enum(item_enum) => attributes.expand(item_enum),
+        Item::Struct(item_struct) => attributes.expand(item_struct),
+        Item::Fn(item_fn) => attributes.expand(item_fn),
+        Item::Mod(item_mod) => attributes.expand(item_mod),
+        Item::Trait(item_trait) => attributes.expand(item_trait),
+        Item::Const(item_const) => attributes.expand(item_const),
+        Item::Static(item_static) => attributes.expand(item_static),
+        Item::Use(item_use) => attributes.expand(item_use),
+        _ => panic!("unsupported item type"),
+    }
+}
+
+/// Mark a previously-`#[unstable]` API as removed.
+///
+/// rustc tracks features that were removed separately from features that
+/// were stabilized, recording the version they were dropped in and a note
+/// pointing users at whatever replaced them. This attribute does the same:
+/// apply it in place of `#[unstable]` once you've decided a gated feature
+/// isn't coming back.
+///
+/// Unlike `#[unstable]`, there's no longer anything worth keeping behind a
+/// `#[cfg]` pair: the item is demoted to `pub(crate)` and given a
+/// `#[deprecated]` attribute so it keeps compiling (and so existing internal
+/// callers get a clear warning), while enabling the old `unstable-*` crate
+/// feature now produces a hard `compile_error!` pointing at the replacement
+/// API and tracking issue, instead of silently doing nothing.
+///
+/// Applying this attribute to non-`pub` items is pointless and does nothing.
+///
+/// # Arguments
+///
+/// - `feature`: The name of the unstable feature that was removed, matching
+///   whatever was previously passed to `#[unstable(feature = "...")]`. This
+///   is required: the catch-all `unstable` feature (used by `#[unstable]`
+///   items with no `feature` argument of their own) is shared crate-wide,
+///   so a removed item defaulting to it would permanently break every
+///   other bare `#[unstable]` item the moment anyone enabled `unstable` to
+///   use them.
+/// - `since`: The crate version in which the feature was removed. This is
+///   required.
+/// - `note`: An explanation of the removal and what replaced it, if
+///   anything. This is required, and is used both in the `#[deprecated]`
+///   note and in the `compile_error!` a consumer gets if they still have the
+///   old feature enabled.
+/// - `issue`: Provide a link or reference to the tracking issue for the
+///   removal. This will be included in the item's documentation.
+///
+/// # Examples
+///
+/// ```
+/// /// This function never really worked out.
+/// #[stability::removed(
+///     feature = "risky-function",
+///     since = "2.0.0",
+///     note = "use `safe_function` instead"
+/// )]
+/// pub fn risky_function() {
+///     unimplemented!()
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn removed(args: TokenStream, input: TokenStream) -> TokenStream {
+    let mut attributes = removed::RemovedAttribute::default();
+    let attributes_parser = syn::meta::parser(|meta| attributes.parse(meta));
+    parse_macro_input!(args with attributes_parser);
+
     match parse_macro_input!(input as Item) {
         Item::Type(item_type) => attributes.expand(item_type),
         Item::Enum(item_enum) => attributes.expand(item_enum),
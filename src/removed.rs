@@ -0,0 +1,109 @@
+use proc_macro::TokenStream;
+use quote::{quote, ToTokens};
+use syn::meta::ParseNestedMeta;
+use syn::parse::Result;
+use syn::parse_quote;
+
+use crate::issue::Issue;
+use crate::unstable::{self, ItemLike};
+
+#[derive(Debug, Default)]
+pub(crate) struct RemovedAttribute {
+    feature: Option<String>,
+    since: Option<String>,
+    note: Option<String>,
+    issue: Option<Issue>,
+}
+
+impl RemovedAttribute {
+    pub(crate) fn parse(&mut self, meta: ParseNestedMeta) -> Result<()> {
+        if meta.path.is_ident("feature") {
+            match meta.value()?.parse()? {
+                syn::Lit::Str(s) => self.feature = Some(s.value()),
+                _ => panic!(),
+            }
+        } else if meta.path.is_ident("since") {
+            match meta.value()?.parse()? {
+                syn::Lit::Str(s) => self.since = Some(s.value()),
+                _ => panic!(),
+            }
+        } else if meta.path.is_ident("note") {
+            match meta.value()?.parse()? {
+                syn::Lit::Str(s) => self.note = Some(s.value()),
+                _ => panic!(),
+            }
+        } else if meta.path.is_ident("issue") {
+            self.issue = Some(Issue::parse(&meta)?);
+        }
+        Ok(())
+    }
+
+    /// Unlike `#[unstable]`, this never hides the item behind a `#[cfg]`
+    /// pair that keeps both a public and a `pub(crate)` copy around: the
+    /// feature is gone, so there is nothing left to gate. Instead, the item
+    /// itself is kept compiling and demoted to `pub(crate)` with a
+    /// `#[deprecated]` attribute, while enabling the old `unstable-*`
+    /// feature (which nobody should still be doing) is turned into a hard
+    /// `compile_error!` pointing at the replacement.
+    pub(crate) fn expand(&self, mut item: impl ItemLike + ToTokens + Clone) -> TokenStream {
+        if !item.is_public() {
+            return item.into_token_stream().into();
+        }
+
+        let feature = self
+            .feature
+            .as_deref()
+            .expect("#[removed] requires a `feature` argument");
+        let feature_name = unstable::crate_feature_name(Some(feature));
+        let since = self
+            .since
+            .as_deref()
+            .expect("#[removed] requires a `since` argument");
+        let note = self
+            .note
+            .as_deref()
+            .expect("#[removed] requires a `note` argument pointing to a replacement");
+
+        let mut doc_addendum = format!(
+            "\n\
+            # Availability\n\
+            \n\
+            **This API was removed in version {}** and is no longer available, \
+            even via the `{}` crate feature.\n\
+            \n\
+            {}",
+            since, feature_name, note
+        );
+
+        if let Some(issue) = &self.issue {
+            doc_addendum.push_str(&format!(
+                "\n\nThe tracking issue was: {}",
+                issue.to_markdown()
+            ));
+        }
+
+        item.push_attr(parse_quote! {
+            #[doc = #doc_addendum]
+        });
+        item.push_attr(parse_quote! {
+            #[deprecated(since = #since, note = #note)]
+        });
+        item.set_visibility(parse_quote! {
+            pub(crate)
+        });
+
+        let error_message = format!(
+            "the `{}` feature was removed in version {}: {}",
+            feature_name, since, note
+        );
+
+        TokenStream::from(quote! {
+            #[cfg(feature = #feature_name)]
+            compile_error!(#error_message);
+
+            #[cfg(not(feature = #feature_name))]
+            #[allow(dead_code, deprecated)]
+            #item
+        })
+    }
+}
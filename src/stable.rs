@@ -0,0 +1,91 @@
+use proc_macro::TokenStream;
+use quote::ToTokens;
+use syn::meta::ParseNestedMeta;
+use syn::parse::Result;
+use syn::parse_quote;
+
+use crate::issue::Issue;
+use crate::unstable::{self, ItemLike};
+
+#[derive(Debug, Default)]
+pub(crate) struct StableAttribute {
+    feature: Option<String>,
+    since: Option<String>,
+    issue: Option<Issue>,
+}
+
+impl StableAttribute {
+    pub(crate) fn parse(&mut self, meta: ParseNestedMeta) -> Result<()> {
+        if meta.path.is_ident("feature") {
+            match meta.value()?.parse()? {
+                syn::Lit::Str(s) => self.feature = Some(s.value()),
+                _ => panic!(),
+            }
+        } else if meta.path.is_ident("since") {
+            match meta.value()?.parse()? {
+                syn::Lit::Str(s) => self.since = Some(s.value()),
+                _ => panic!(),
+            }
+        } else if meta.path.is_ident("issue") {
+            self.issue = Some(Issue::parse(&meta)?);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn expand(&self, mut item: impl ItemLike + ToTokens) -> TokenStream {
+        let feature = self.feature.as_deref().expect(
+            "#[stable] requires a `feature` argument naming the feature being stabilized",
+        );
+        let since = self
+            .since
+            .as_deref()
+            .expect("#[stable] requires a `since` argument");
+
+        // If some other item in the crate is still gated behind an
+        // `#[unstable]` feature with this name, flipping this one to stable
+        // was probably a mistake (or at least an incomplete stabilization),
+        // so refuse to compile instead of silently shipping a
+        // half-stabilized API. The feature name is taken from the `feature`
+        // argument above, not guessed from the item's identifier, since
+        // there's no guarantee the two match.
+        //
+        // This is best-effort: it only fires if the `#[unstable]` usage
+        // expanded before this one does, which in practice means it has to
+        // appear earlier in the crate's source. See the caveat on this
+        // macro's documentation in lib.rs.
+        if unstable::is_still_unstable(feature) {
+            let message = format!(
+                "the `{}` feature is marked #[stable], but is still referenced by an \
+                #[unstable] item elsewhere in the crate; remove that attribute (or rename \
+                the feature) before stabilizing",
+                feature
+            );
+
+            return TokenStream::from(quote::quote! {
+                compile_error!(#message);
+                #item
+            });
+        }
+
+        let mut doc_addendum = format!(
+            "\n\
+            # Availability\n\
+            \n\
+            **Stabilized in version {}.**",
+            since
+        );
+
+        if let Some(issue) = &self.issue {
+            doc_addendum.push_str(&format!(
+                "\nThe tracking issue was: {}",
+                issue.to_markdown()
+            ));
+        }
+
+        item.push_attr(parse_quote! {
+            #[doc = #doc_addendum]
+        });
+
+        item.into_token_stream().into()
+    }
+}
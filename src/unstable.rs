@@ -1,13 +1,48 @@
+use std::collections::HashSet;
+use std::sync::{Mutex, OnceLock};
+
 use proc_macro::TokenStream;
-use quote::{quote, ToTokens};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote, ToTokens};
 use syn::meta::ParseNestedMeta;
 use syn::parse::Result;
-use syn::{parse_quote, Visibility};
+use syn::{parse_quote, Item, Visibility};
+
+use crate::issue::Issue;
+
+/// Features that have been seen guarding an `#[unstable]` item so far during
+/// this compilation.
+///
+/// This is used by the [`stable`](crate::stable) attribute to detect when an
+/// item is being stabilized while some other item in the crate is still
+/// gated behind the same feature name.
+///
+/// This is inherently best-effort, not a real cross-item analysis: proc
+/// macros don't run in a guaranteed order, so a feature only ends up in
+/// this set if its `#[unstable]` usage happens to expand before the
+/// `#[stable]` usage that checks for it, which in practice means it needs
+/// to appear earlier in the crate's source. See the caveat on
+/// [`stable`](crate::stable)'s top-level documentation.
+fn unstable_features() -> &'static Mutex<HashSet<String>> {
+    static FEATURES: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+    FEATURES.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+/// Best-effort only; see [`unstable_features`] for why this can miss a
+/// still-unstable feature depending on macro-expansion order.
+pub(crate) fn is_still_unstable(feature: &str) -> bool {
+    unstable_features()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .contains(feature)
+}
 
 #[derive(Debug, Default)]
 pub(crate) struct UnstableAttribute {
     feature: Option<String>,
-    issue: Option<String>,
+    issue: Option<Issue>,
+    reason: Option<String>,
+    recursive: bool,
 }
 
 impl UnstableAttribute {
@@ -18,76 +53,338 @@ impl UnstableAttribute {
                 _ => panic!(),
             }
         } else if meta.path.is_ident("issue") {
+            self.issue = Some(Issue::parse(&meta)?);
+        } else if meta.path.is_ident("reason") {
             match meta.value()?.parse()? {
-                syn::Lit::Str(s) => self.issue = Some(s.value()),
+                syn::Lit::Str(s) => self.reason = Some(s.value()),
                 _ => panic!(),
             }
+        } else if meta.path.is_ident("recursive") {
+            self.recursive = true;
         }
         Ok(())
     }
 
     fn crate_feature_name(&self) -> String {
-        if let Some(name) = self.feature.as_deref() {
-            format!("unstable-{}", name)
+        crate_feature_name(self.feature.as_deref())
+    }
+
+    /// Dispatch on whether this is a recursive `#[unstable]` applied to a
+    /// module with inline content; everything else goes through the normal,
+    /// single-item expansion.
+    pub(crate) fn expand_item_mod(&self, item_mod: syn::ItemMod) -> TokenStream {
+        if self.recursive && item_mod.content.is_some() {
+            self.expand_recursive(item_mod)
         } else {
-            String::from("unstable")
+            self.expand(item_mod)
         }
     }
 
     pub(crate) fn expand(&self, mut item: impl ItemLike + ToTokens + Clone) -> TokenStream {
-        // We only care about public items.
         if item.is_public() {
-            let feature_name = self.crate_feature_name();
-
-            if let Some(issue) = &self.issue {
-                let doc_addendum = format!(
-                    "\n\
-                    # Availability\n\
-                    \n\
-                    **This API is marked as unstable** and is only available when \
-                    the `{}` crate feature is enabled. This comes with no stability \
-                    guarantees, and could be changed or removed at any time.\
-                    \n\
-                    The tracking issue is: `{}`\
-                ",
-                    feature_name, issue
-                );
-                item.push_attr(parse_quote! {
-                    #[doc = #doc_addendum]
-                });
-            } else {
-                let doc_addendum = format!(
-                    "\n\
-                    # Availability\n\
-                    \n\
-                    **This API is marked as unstable** and is only available when \
-                    the `{}` crate feature is enabled. This comes with no stability \
-                    guarantees, and could be changed or removed at any time.\
-                ",
-                    feature_name
-                );
-                item.push_attr(parse_quote! {
-                    #[doc = #doc_addendum]
-                });
-            }
+            let assoc_item_ident = item.is_assoc_item().then(|| item.ident()).flatten();
+            let (feature_name, feature_check, doc_addendum) = self.prepare(assoc_item_ident);
 
-            let mut hidden_item = item.clone();
-            hidden_item.set_visibility(parse_quote! {
-                pub(crate)
+            item.push_attr(parse_quote! {
+                #[doc = #doc_addendum]
             });
 
+            let gated = gate_pair(&feature_name, item);
+
             TokenStream::from(quote! {
-                #[cfg(feature = #feature_name)]
-                #item
+                #feature_check
+                #gated
+            })
+        } else if item.is_assoc_item() {
+            // A non-`pub` associated function, const, or type is ambiguous:
+            // it might genuinely be a private inherent item (pointless but
+            // harmless to gate), or it might be inside a trait impl, where
+            // items can never carry a `pub` keyword of their own no matter
+            // how public the implemented trait's method is. Silently doing
+            // nothing would leave that second, much more common case fully
+            // exposed with no cfg and no diagnostic, which defeats the
+            // entire point of the attribute. Refuse to compile instead; a
+            // spurious error on a private inherent item is far cheaper than
+            // a trait-impl method shipping unstable and fully public.
+            let message = match item.ident() {
+                Some(ident) => format!(
+                    "`#[unstable]` has no effect on `{}`: items inside a trait impl can never \
+                    be `pub`, so this is indistinguishable here from a private inherent item. \
+                    Gate the method on the trait definition, or on an inherent impl, instead",
+                    ident
+                ),
+                None => String::from(
+                    "`#[unstable]` has no effect here: items inside a trait impl can never be \
+                    `pub`, so this is indistinguishable here from a private inherent item. Gate \
+                    the method on the trait definition, or on an inherent impl, instead",
+                ),
+            };
 
-                #[cfg(not(feature = #feature_name))]
-                #[allow(dead_code)]
-                #hidden_item
+            TokenStream::from(quote! {
+                compile_error!(#message);
+                #item
             })
         } else {
             item.into_token_stream().into()
         }
     }
+
+    /// A trait method, associated const, or associated type inside a `trait`
+    /// block. Unlike free items and impl items, trait items have no
+    /// visibility of their own to toggle, so they're gated differently: see
+    /// [`expand_trait_fn`](Self::expand_trait_fn) and
+    /// [`expand_trait_assoc`](Self::expand_trait_assoc).
+    pub(crate) fn expand_trait_item(&self, item: syn::TraitItem) -> TokenStream {
+        match item {
+            syn::TraitItem::Fn(item_fn) => self.expand_trait_fn(item_fn),
+            syn::TraitItem::Const(item_const) => self.expand_trait_assoc(item_const),
+            syn::TraitItem::Type(item_type) => self.expand_trait_assoc(item_type),
+            other => other.into_token_stream().into(),
+        }
+    }
+
+    /// Gate a trait method behind a feature. Since hiding the method
+    /// entirely would force every implementor of the trait to suddenly
+    /// implement an unstable method just to keep compiling, the method is
+    /// instead given a default body (that panics) when the feature is off,
+    /// so the trait stays implementable either way.
+    fn expand_trait_fn(&self, mut item_fn: syn::TraitItemFn) -> TokenStream {
+        let (feature_name, feature_check, doc_addendum) = self.prepare(Some(&item_fn.sig.ident));
+
+        item_fn.attrs.push(parse_quote! {
+            #[doc = #doc_addendum]
+        });
+
+        let mut fallback = item_fn.clone();
+        let message = format!(
+            "the `{}` method is gated behind the `{}` feature, which is not enabled",
+            item_fn.sig.ident, feature_name
+        );
+        fallback.default = Some(parse_quote! {{
+            unimplemented!(#message)
+        }});
+        fallback.semi_token = None;
+
+        TokenStream::from(quote! {
+            #feature_check
+
+            #[cfg(feature = #feature_name)]
+            #item_fn
+
+            #[cfg(not(feature = #feature_name))]
+            #fallback
+        })
+    }
+
+    /// Gate a trait associated const or associated type behind a feature.
+    /// These simply disappear from the trait's definition when the feature
+    /// is off, which is fine since neither affects whether an existing
+    /// implementation of the trait still compiles.
+    fn expand_trait_assoc(&self, mut item: impl TraitAssocItem + ToTokens) -> TokenStream {
+        let (feature_name, feature_check, doc_addendum) = self.prepare(Some(item.ident()));
+
+        item.push_attr(parse_quote! {
+            #[doc = #doc_addendum]
+        });
+
+        TokenStream::from(quote! {
+            #feature_check
+
+            #[cfg(feature = #feature_name)]
+            #item
+        })
+    }
+
+    /// Compute the feature's crate-feature name, its `features!` marker
+    /// check (if named), and the Availability doc section, while recording
+    /// the feature name as currently in use so [`stable`](crate::stable) can
+    /// detect a premature stabilization.
+    ///
+    /// `assoc_item_ident` must be the item's own identifier when gating an
+    /// associated function, const, or type inside an `impl`/`trait` block,
+    /// and `None` for a top-level item. An anonymous `const _: () = ..;` is
+    /// legal Rust at module scope, but not as an associated item, so the
+    /// marker check needs a real (if uninteresting) name in that position.
+    fn prepare(
+        &self,
+        assoc_item_ident: Option<&syn::Ident>,
+    ) -> (String, Option<TokenStream2>, String) {
+        let feature_name = self.crate_feature_name();
+        let feature_check = self.feature.as_deref().map(|feature| match assoc_item_ident {
+            Some(ident) => assoc_feature_check_tokens(feature, ident),
+            None => feature_check_tokens(feature),
+        });
+
+        if let Some(feature) = &self.feature {
+            unstable_features()
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(feature.clone());
+        }
+
+        let doc_addendum =
+            availability_doc(&feature_name, self.issue.as_ref(), self.reason.as_deref());
+
+        (feature_name, feature_check, doc_addendum)
+    }
+
+    /// Walk the module's inline content, gating every public child item
+    /// behind the same feature, descending into nested modules as well, then
+    /// gate the module itself exactly as the non-recursive case would.
+    fn expand_recursive(&self, mut item_mod: syn::ItemMod) -> TokenStream {
+        let feature_name = self.crate_feature_name();
+
+        if let Some((brace, items)) = item_mod.content.take() {
+            let items = items
+                .into_iter()
+                .map(|item| gate_child(&feature_name, self.reason.as_deref(), item))
+                .collect();
+            item_mod.content = Some((brace, items));
+        }
+
+        self.expand(item_mod)
+    }
+}
+
+/// Compute the crate feature name for an optional `#[unstable(feature =
+/// "...")]` argument, prepending `unstable-` to a named feature, or falling
+/// back to the catch-all `unstable` feature if none was given.
+///
+/// Shared with [`removed`](crate::removed), which gates the same way.
+pub(crate) fn crate_feature_name(feature: Option<&str>) -> String {
+    match feature {
+        Some(name) => format!("unstable-{}", name),
+        None => String::from("unstable"),
+    }
+}
+
+/// Recursively gate a single child item of a module that was marked
+/// `#[unstable(.., recursive)]`. Items that aren't `pub`, and items that
+/// don't carry a visibility at all (like `impl` blocks), pass through
+/// unchanged.
+///
+/// A gated item expands to a `#[cfg]`-gated pair (see [`gate_pair`]), so the
+/// result is wrapped as a single `Item::Verbatim` carrying both halves'
+/// tokens rather than a single replacement `Item`.
+fn gate_child(feature_name: &str, reason: Option<&str>, item: Item) -> Item {
+    match item {
+        Item::Type(inner) => gate_or_pass(feature_name, reason, inner),
+        Item::Enum(inner) => gate_or_pass(feature_name, reason, inner),
+        Item::Struct(inner) => gate_or_pass(feature_name, reason, inner),
+        Item::Fn(inner) => gate_or_pass(feature_name, reason, inner),
+        Item::Trait(inner) => gate_or_pass(feature_name, reason, inner),
+        Item::Const(inner) => gate_or_pass(feature_name, reason, inner),
+        Item::Static(inner) => gate_or_pass(feature_name, reason, inner),
+        Item::Use(inner) => gate_or_pass(feature_name, reason, inner),
+        Item::Mod(mut inner) if inner.content.is_some() => {
+            if !inner.is_public() {
+                return Item::Mod(inner);
+            }
+
+            if let Some((brace, items)) = inner.content.take() {
+                let items = items
+                    .into_iter()
+                    .map(|item| gate_child(feature_name, reason, item))
+                    .collect();
+                inner.content = Some((brace, items));
+            }
+
+            gate_or_pass(feature_name, reason, inner)
+        }
+        other => other,
+    }
+}
+
+/// Gate a public child item behind `feature_name`, or pass it through
+/// unchanged if it isn't `pub`. The gated form expands to a `#[cfg]`-gated
+/// pair (see [`gate_pair`]), so it is wrapped as a single `Item::Verbatim`
+/// carrying both halves' tokens rather than a single replacement `Item`.
+fn gate_or_pass<T>(feature_name: &str, reason: Option<&str>, mut inner: T) -> Item
+where
+    T: ItemLike + ToTokens + Clone,
+    Item: From<T>,
+{
+    if !inner.is_public() {
+        return Item::from(inner);
+    }
+
+    let doc_addendum = availability_doc(feature_name, None, reason);
+    inner.push_attr(parse_quote! {
+        #[doc = #doc_addendum]
+    });
+
+    Item::Verbatim(gate_pair(feature_name, inner))
+}
+
+fn feature_check_tokens(feature: &str) -> TokenStream2 {
+    let marker = format_ident!("{}", feature.replace('-', "_"));
+
+    quote! {
+        #[allow(non_upper_case_globals)]
+        const _: () = crate::__unstable_features::#marker;
+    }
+}
+
+/// The same `features!` marker check as [`feature_check_tokens`], but for a
+/// position where an anonymous `const _: () = ..;` isn't legal syntax: an
+/// associated item inside an `impl` or `trait` block. The check const is
+/// instead given a name derived from the gated item's own identifier, which
+/// is guaranteed not to collide with another associated item in the same
+/// block (it differs from every sibling's name by its prefix) or with the
+/// check generated for a different sibling (sibling identifiers are
+/// themselves unique within the block).
+fn assoc_feature_check_tokens(feature: &str, item_ident: &syn::Ident) -> TokenStream2 {
+    let marker = format_ident!("{}", feature.replace('-', "_"));
+    let check_name = format_ident!("__unstable_feature_check_{}", item_ident);
+
+    quote! {
+        #[allow(non_upper_case_globals)]
+        const #check_name: () = crate::__unstable_features::#marker;
+    }
+}
+
+fn availability_doc(feature_name: &str, issue: Option<&Issue>, reason: Option<&str>) -> String {
+    let mut doc = format!(
+        "\n\
+        # Availability\n\
+        \n\
+        **This API is marked as unstable** and is only available when \
+        the `{}` crate feature is enabled. This comes with no stability \
+        guarantees, and could be changed or removed at any time.",
+        feature_name
+    );
+
+    if let Some(reason) = reason {
+        doc.push_str(&format!("\n\n{}", reason));
+    }
+
+    if let Some(issue) = issue {
+        doc.push_str(&format!(
+            "\n\nThe tracking issue is: {}",
+            issue.to_markdown()
+        ));
+    }
+
+    doc
+}
+
+/// Split a single public item into a `#[cfg]`-gated public half and a
+/// `pub(crate)` fallback half, without touching its documentation (callers
+/// are expected to have already appended the Availability section).
+fn gate_pair(feature_name: &str, item: impl ItemLike + ToTokens + Clone) -> TokenStream2 {
+    let mut hidden_item = item.clone();
+    hidden_item.set_visibility(parse_quote! {
+        pub(crate)
+    });
+
+    quote! {
+        #[cfg(feature = #feature_name)]
+        #item
+
+        #[cfg(not(feature = #feature_name))]
+        #[allow(dead_code)]
+        #hidden_item
+    }
 }
 
 pub(crate) trait ItemLike {
@@ -99,13 +396,29 @@ pub(crate) trait ItemLike {
 
     fn set_visibility(&mut self, visibility: Visibility);
 
+    /// The identifier naming this item, if it has one.
+    ///
+    /// `use` items have no identifier of their own and return `None`.
+    fn ident(&self) -> Option<&syn::Ident> {
+        None
+    }
+
+    /// Whether this item is an associated function, const, or type inside
+    /// an `impl`/`trait` block, as opposed to a top-level item. An
+    /// anonymous `const _: () = ..;` is only legal syntax at the latter, so
+    /// callers that need to emit one have to know which of the two they're
+    /// dealing with.
+    fn is_assoc_item(&self) -> bool {
+        false
+    }
+
     fn is_public(&self) -> bool {
         matches!(self.visibility(), Visibility::Public(_))
     }
 }
 
 macro_rules! impl_has_visibility {
-    ($($ty:ty),+ $(,)?) => {
+    ($(($ty:ty, $is_assoc_item:expr)),+ $(,)?) => {
         $(
             impl ItemLike for $ty {
                 fn attrs(&self) -> &[syn::Attribute] {
@@ -123,22 +436,101 @@ macro_rules! impl_has_visibility {
                 fn set_visibility(&mut self, visibility: Visibility) {
                     self.vis = visibility;
                 }
+
+                fn ident(&self) -> Option<&syn::Ident> {
+                    Some(&self.ident)
+                }
+
+                fn is_assoc_item(&self) -> bool {
+                    $is_assoc_item
+                }
             }
         )*
     };
 }
 
 impl_has_visibility!(
-    syn::ItemType,
-    syn::ItemEnum,
-    syn::ItemFn,
-    syn::ItemMod,
-    syn::ItemTrait,
-    syn::ItemConst,
-    syn::ItemStatic,
-    syn::ItemUse,
+    (syn::ItemType, false),
+    (syn::ItemEnum, false),
+    (syn::ItemMod, false),
+    (syn::ItemTrait, false),
+    (syn::ItemConst, false),
+    (syn::ItemStatic, false),
+    (syn::ImplItemConst, true),
+    (syn::ImplItemType, true),
 );
 
+impl ItemLike for syn::ItemFn {
+    fn attrs(&self) -> &[syn::Attribute] {
+        &self.attrs
+    }
+
+    fn push_attr(&mut self, attr: syn::Attribute) {
+        self.attrs.push(attr);
+    }
+
+    fn visibility(&self) -> &Visibility {
+        &self.vis
+    }
+
+    fn set_visibility(&mut self, visibility: Visibility) {
+        self.vis = visibility;
+    }
+
+    fn ident(&self) -> Option<&syn::Ident> {
+        Some(&self.sig.ident)
+    }
+}
+
+/// Note that a method inside a *trait impl* (as opposed to an inherent
+/// impl) can never carry a `pub` keyword of its own, so `is_public()` always
+/// reports it as not public there, and `#[unstable]` turns it into a hard
+/// `compile_error!`, exactly as it would a private inherent method. See the `impl`
+/// block caveat on the `unstable` attribute's top-level documentation.
+impl ItemLike for syn::ImplItemFn {
+    fn attrs(&self) -> &[syn::Attribute] {
+        &self.attrs
+    }
+
+    fn push_attr(&mut self, attr: syn::Attribute) {
+        self.attrs.push(attr);
+    }
+
+    fn visibility(&self) -> &Visibility {
+        &self.vis
+    }
+
+    fn set_visibility(&mut self, visibility: Visibility) {
+        self.vis = visibility;
+    }
+
+    fn ident(&self) -> Option<&syn::Ident> {
+        Some(&self.sig.ident)
+    }
+
+    fn is_assoc_item(&self) -> bool {
+        true
+    }
+}
+
+impl ItemLike for syn::ItemUse {
+    fn attrs(&self) -> &[syn::Attribute] {
+        &self.attrs
+    }
+
+    fn push_attr(&mut self, attr: syn::Attribute) {
+        self.attrs.push(attr);
+    }
+
+    fn visibility(&self) -> &Visibility {
+        &self.vis
+    }
+
+    fn set_visibility(&mut self, visibility: Visibility) {
+        self.vis = visibility;
+    }
+}
+
 impl ItemLike for syn::ItemStruct {
     fn attrs(&self) -> &[syn::Attribute] {
         &self.attrs
@@ -162,4 +554,39 @@ impl ItemLike for syn::ItemStruct {
 
         self.vis = visibility;
     }
+
+    fn ident(&self) -> Option<&syn::Ident> {
+        Some(&self.ident)
+    }
+}
+
+/// An associated const or associated type inside a `trait` block. These
+/// have no visibility of their own, unlike [`ItemLike`] items and impl
+/// items, so they only need a way to attach the Availability doc and to
+/// identify themselves for the `features!` marker check, which can't use
+/// an anonymous const in associated-item position.
+pub(crate) trait TraitAssocItem {
+    fn push_attr(&mut self, attr: syn::Attribute);
+
+    fn ident(&self) -> &syn::Ident;
+}
+
+impl TraitAssocItem for syn::TraitItemConst {
+    fn push_attr(&mut self, attr: syn::Attribute) {
+        self.attrs.push(attr);
+    }
+
+    fn ident(&self) -> &syn::Ident {
+        &self.ident
+    }
+}
+
+impl TraitAssocItem for syn::TraitItemType {
+    fn push_attr(&mut self, attr: syn::Attribute) {
+        self.attrs.push(attr);
+    }
+
+    fn ident(&self) -> &syn::Ident {
+        &self.ident
+    }
 }